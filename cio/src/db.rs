@@ -0,0 +1,33 @@
+use std::sync::Mutex;
+
+use crate::models::{GithubRepo, NewRepo};
+
+/// Our database of synced GitHub repos, scoped per org so two orgs can have
+/// same-named repos without colliding.
+pub struct Database {
+    github_repos: Mutex<Vec<GithubRepo>>,
+}
+
+impl Database {
+    pub fn new() -> Self {
+        Database { github_repos: Mutex::new(Vec::new()) }
+    }
+
+    pub fn get_github_repos(&self) -> Vec<GithubRepo> {
+        self.github_repos.lock().unwrap().clone()
+    }
+
+    pub fn upsert_github_repo(&self, new_repo: &NewRepo) {
+        let mut repos = self.github_repos.lock().unwrap();
+        match repos.iter_mut().find(|r| r.name == new_repo.name && r.organization == new_repo.organization) {
+            Some(existing) => *existing = new_repo.clone().into(),
+            None => repos.push(new_repo.clone().into()),
+        }
+    }
+
+    /// Delete a repo by name, scoped to its org so dropping out of one org
+    /// doesn't remove a same-named repo still owned by another.
+    pub fn delete_github_repo_by_name(&self, name: &str, organization: &str) {
+        self.github_repos.lock().unwrap().retain(|r| !(r.name == name && r.organization == organization));
+    }
+}
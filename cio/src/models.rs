@@ -0,0 +1,32 @@
+/// A GitHub repository synced from one of our configured orgs into the database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GithubRepo {
+    pub name: String,
+    pub organization: String,
+}
+
+/// A `GithubRepo` on its way into the database, built from a hubcaps repo.
+#[derive(Debug, Clone)]
+pub struct NewRepo {
+    pub name: String,
+    pub organization: String,
+}
+
+impl NewRepo {
+    /// Build a `NewRepo` from a hubcaps repo, tagging it with the org it was fetched from.
+    pub async fn new(repo: hubcaps::repositories::Repo, organization: String) -> Self {
+        NewRepo {
+            name: repo.name,
+            organization,
+        }
+    }
+}
+
+impl From<NewRepo> for GithubRepo {
+    fn from(new_repo: NewRepo) -> Self {
+        GithubRepo {
+            name: new_repo.name,
+            organization: new_repo.organization,
+        }
+    }
+}
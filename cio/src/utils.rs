@@ -2,13 +2,10 @@ use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::io::Write;
-use std::ops::Add;
 use std::path::PathBuf;
 use std::str::from_utf8;
-use std::thread;
-use std::time;
 
-use futures_util::stream::TryStreamExt;
+use futures_util::stream::{self, StreamExt, TryStreamExt};
 use hubcaps::http_cache::FileBasedCache;
 use hubcaps::issues::Issue;
 use hubcaps::repositories::{OrgRepoType, OrganizationRepoListOptions, Repository};
@@ -21,6 +18,18 @@ use yup_oauth2::{read_service_account_key, AccessToken, ServiceAccountAuthentica
 use crate::db::Database;
 use crate::models::{GithubRepo, NewRepo};
 
+mod app_auth;
+mod git_data;
+mod github_info;
+mod pull_request;
+mod retry;
+
+pub use app_auth::GithubAppAuth;
+pub use git_data::commit_files_in_github_repo;
+pub use github_info::GithubInfo;
+pub use pull_request::{check_if_github_pr_exists, open_or_update_pull_request_with_files};
+pub use retry::{retry_github, retry_http};
+
 /// Write a file.
 #[instrument]
 #[inline]
@@ -160,26 +169,69 @@ pub fn authenticate_github_jwt() -> Github {
     )
 }
 
+/// Authenticate with GitHub as our App installation, auto-refreshing the
+/// installation token as it approaches expiry. Prefer this over
+/// `authenticate_github_jwt` for anything long-running: call `client()`
+/// before each request instead of holding onto a single `Github` built once
+/// at startup.
 #[instrument]
 #[inline]
-pub fn github_org() -> String {
-    env::var("GITHUB_ORG").unwrap()
+pub fn authenticate_github_app() -> GithubAppAuth {
+    let installation_id_str = env::var("GH_INSTALLATION_ID").unwrap();
+    let installation_id = installation_id_str.parse::<u64>().unwrap();
+
+    let app_id_str = env::var("GH_APP_ID").unwrap();
+    let app_id = app_id_str.parse::<u64>().unwrap();
+    let encoded_private_key = env::var("GH_PRIVATE_KEY").unwrap();
+    let private_key = base64::decode(encoded_private_key).unwrap();
+    let key = nom_pem::decode_block(&private_key).unwrap();
+    let jwt = JWTCredentials::new(app_id, key.data).unwrap();
+
+    GithubAppAuth::new(installation_id, jwt)
 }
 
-/// List all the GitHub repositories for our org.
+/// The GitHub organizations we sync, parsed from a comma-separated
+/// `GITHUB_ORGS` (falling back to the single-org `GITHUB_ORG` for
+/// compatibility).
+#[instrument]
+#[inline]
+pub fn github_orgs() -> Vec<String> {
+    let orgs = env::var("GITHUB_ORGS").or_else(|_| env::var("GITHUB_ORG")).unwrap();
+
+    orgs.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()
+}
+
+/// Default number of repos we enrich (fetch + build a `NewRepo` for) at once.
+/// Overridable via the `PARALLEL_REPO_FETCHES` env variable.
+const PARALLEL_REPO_FETCHES: usize = 32;
+
+/// Number of repos to fetch/enrich concurrently, from `PARALLEL_REPO_FETCHES`
+/// if set, falling back to the default above.
+fn parallel_repo_fetches() -> usize {
+    env::var("PARALLEL_REPO_FETCHES").ok().and_then(|v| v.parse().ok()).unwrap_or(PARALLEL_REPO_FETCHES)
+}
+
+/// List all the GitHub repositories across all our configured orgs.
 #[instrument]
 #[inline]
 pub async fn list_all_github_repos(github: &Github) -> Vec<NewRepo> {
-    let github_repos = github
-        .org_repos(github_org())
-        .iter(&OrganizationRepoListOptions::builder().per_page(100).repo_type(OrgRepoType::All).build())
-        .try_collect::<Vec<hubcaps::repositories::Repo>>()
+    let mut repos = Vec::new();
+
+    for org in github_orgs() {
+        let github_repos = retry_github(|| {
+            github
+                .org_repos(org.clone())
+                .iter(&OrganizationRepoListOptions::builder().per_page(100).repo_type(OrgRepoType::All).build())
+                .try_collect::<Vec<hubcaps::repositories::Repo>>()
+        })
         .await
         .unwrap();
 
-    let mut repos: Vec<NewRepo> = Default::default();
-    for r in github_repos {
-        repos.push(NewRepo::new(r).await);
+        // Fetch/enrich each repo into a `NewRepo` concurrently, bounded so we don't
+        // trip secondary rate limits on orgs with hundreds of repos.
+        let org_repos: Vec<NewRepo> = stream::iter(github_repos).map(|r| NewRepo::new(r, org.clone())).buffer_unordered(parallel_repo_fetches()).collect().await;
+
+        repos.extend(org_repos);
     }
 
     repos
@@ -193,28 +245,39 @@ pub async fn refresh_db_github_repos(github: &Github) {
 
     // Initialize our database.
     let db = Database::new();
+    let github_info = GithubInfo::new(&env::var("GITHUB_TOKEN").unwrap());
 
-    // Get all the repos.
-    let db_repos = db.get_github_repos();
-    // Create a BTreeMap
-    let mut repo_map: BTreeMap<String, GithubRepo> = Default::default();
-    for r in db_repos {
-        repo_map.insert(r.name.to_string(), r);
+    // Get all the repos, grouped by the org they belong to so that dropping
+    // out of one org doesn't wipe repos that belong to another.
+    let mut repo_maps: BTreeMap<String, BTreeMap<String, GithubRepo>> = Default::default();
+    for r in db.get_github_repos() {
+        repo_maps.entry(r.organization.clone()).or_insert_with(BTreeMap::new).insert(r.name.to_string(), r);
     }
 
     // Sync github_repos.
     for github_repo in github_repos {
-        db.upsert_github_repo(&github_repo);
+        // Remove the repo from its org's map.
+        if let Some(repo_map) = repo_maps.get_mut(&github_repo.organization) {
+            repo_map.remove(&github_repo.name);
+        }
+
+        // Log contributor counts so we can flag repos that picked up external
+        // contributors, without spending extra rate limit on repos we've
+        // already seen (thanks to github_info's conditional-request cache).
+        if let Some(contributors) = github_info.contributors(&github_repo.organization, &github_repo.name).await {
+            println!("[github repos] {}/{} has {} contributor(s)", github_repo.organization, github_repo.name, contributors.len());
+        }
 
-        // Remove the repo from the map.
-        repo_map.remove(&github_repo.name);
+        db.upsert_github_repo(&github_repo);
     }
 
     // Remove any repos that should no longer be in the database.
-    // This is found by the remaining repos that are in the map since we removed
-    // the existing repos from the map above.
-    for (name, _) in repo_map {
-        db.delete_github_repo_by_name(&name);
+    // This is found by the remaining repos that are in each org's map since we
+    // removed the existing repos from the map above.
+    for (organization, repo_map) in repo_maps {
+        for (name, _) in repo_map {
+            db.delete_github_repo_by_name(&name, &organization);
+        }
     }
 }
 
@@ -227,7 +290,7 @@ pub async fn create_or_update_file_in_github_repo(repo: &Repository, branch: &st
     let content = new_content.trim();
 
     // Try to get the content for the file from the repo.
-    match repo.content().file(file_path, branch).await {
+    match retry_github(|| repo.content().file(file_path, branch)).await {
         Ok(file) => {
             let file_content: Vec<u8> = file.content.into();
             let decoded = file_content.trim();
@@ -254,8 +317,8 @@ pub async fn create_or_update_file_in_github_repo(repo: &Repository, branch: &st
             }
 
             // We need to update the file. Ignore failure.
-            repo.content()
-                .update(
+            retry_github(|| {
+                repo.content().update(
                     file_path,
                     &content,
                     &format!(
@@ -265,18 +328,14 @@ pub async fn create_or_update_file_in_github_repo(repo: &Repository, branch: &st
                     &file.sha,
                     branch,
                 )
-                .await
-                .ok();
+            })
+            .await
+            .ok();
 
             println!("[github content] Updated file at {}", file_path);
         }
         Err(e) => {
             match e {
-                hubcaps::errors::Error::RateLimit { reset } => {
-                    // We got a rate limit error.
-                    println!("got rate limited, sleeping for {}s", reset.as_secs());
-                    thread::sleep(reset.add(time::Duration::from_secs(5)));
-                }
                 hubcaps::errors::Error::Fault { code: _, error } => {
                     if error.message.contains("too_large") {
                         // The file is too big for us to get it's contents through this API.
@@ -288,7 +347,10 @@ pub async fn create_or_update_file_in_github_repo(repo: &Repository, branch: &st
                         let mut path = PathBuf::from(file_path);
                         path.pop();
 
-                        for item in repo.content().iter(path.to_str().unwrap(), branch).try_collect::<Vec<hubcaps::content::DirectoryItem>>().await.unwrap() {
+                        for item in retry_github(|| repo.content().iter(path.to_str().unwrap(), branch).try_collect::<Vec<hubcaps::content::DirectoryItem>>())
+                            .await
+                            .unwrap()
+                        {
                             if file_path.trim_start_matches('/') != item.path {
                                 // Continue early.
                                 continue;
@@ -297,7 +359,7 @@ pub async fn create_or_update_file_in_github_repo(repo: &Repository, branch: &st
                             // Otherwise, this is our file.
                             // We have the sha we can see if the files match using the
                             // Git Data API.
-                            let blob = repo.git().blob(&item.sha).await.unwrap();
+                            let blob = retry_github(|| repo.git().blob(&item.sha)).await.unwrap();
                             // Base64 decode the contents.
                             // TODO: move this logic to hubcaps.
                             let v = blob.content.replace("\n", "");
@@ -313,8 +375,8 @@ pub async fn create_or_update_file_in_github_repo(repo: &Repository, branch: &st
                             }
 
                             // We can actually update the file since we have the sha.
-                            repo.content()
-                                .update(
+                            retry_github(|| {
+                                repo.content().update(
                                     file_path,
                                     &content,
                                     &format!(
@@ -324,8 +386,9 @@ pub async fn create_or_update_file_in_github_repo(repo: &Repository, branch: &st
                                     &item.sha,
                                     branch,
                                 )
-                                .await
-                                .ok();
+                            })
+                            .await
+                            .ok();
 
                             println!("[github content] Updated file at {}", file_path);
 
@@ -340,8 +403,8 @@ pub async fn create_or_update_file_in_github_repo(repo: &Repository, branch: &st
             }
 
             // Create the file in the repo. Ignore failure.
-            repo.content()
-                .create(
+            retry_github(|| {
+                repo.content().create(
                     file_path,
                     &content,
                     &format!(
@@ -350,8 +413,9 @@ pub async fn create_or_update_file_in_github_repo(repo: &Repository, branch: &st
                     ),
                     branch,
                 )
-                .await
-                .ok();
+            })
+            .await
+            .ok();
 
             println!("[github content] Created file at {}", file_path);
         }
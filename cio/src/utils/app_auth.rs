@@ -0,0 +1,86 @@
+use std::env;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use chrono::DateTime;
+use hubcaps::http_cache::FileBasedCache;
+use hubcaps::{Credentials, Github, JWTCredentials};
+use reqwest::Client;
+use tokio::sync::RwLock;
+use tracing::instrument;
+
+/// How close to expiry we re-mint the installation token, so a request in
+/// flight never races against the token actually expiring.
+const EXPIRY_SKEW: Duration = Duration::from_secs(60);
+
+struct CachedToken {
+    token: String,
+    expires_at: SystemTime,
+}
+
+/// Hands out a `Github` client backed by a live installation token,
+/// transparently re-minting it when it's missing or close to expiry.
+pub struct GithubAppAuth {
+    installation_id: u64,
+    jwt: JWTCredentials,
+    cached: Arc<RwLock<Option<CachedToken>>>,
+}
+
+impl GithubAppAuth {
+    /// Create a new `GithubAppAuth` for the given installation, authenticated
+    /// via `jwt`.
+    pub fn new(installation_id: u64, jwt: JWTCredentials) -> Self {
+        GithubAppAuth {
+            installation_id,
+            jwt,
+            cached: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Get a `Github` client authenticated with a live installation token,
+    /// re-minting it first if it's missing or close to expiry.
+    #[instrument(skip(self))]
+    pub async fn client(&self) -> Github {
+        let needs_refresh = match &*self.cached.read().await {
+            Some(cached) => cached.expires_at <= SystemTime::now() + EXPIRY_SKEW,
+            None => true,
+        };
+
+        if needs_refresh {
+            let (token, expires_at) = self.mint_installation_token().await;
+            *self.cached.write().await = Some(CachedToken { token, expires_at });
+            println!("[github app auth] minted a new installation token, expires at {:?}", expires_at);
+        }
+
+        let token = self.cached.read().await.as_ref().unwrap().token.clone();
+        github_client(Credentials::Token(token))
+    }
+
+    /// Exchange our JWT for a fresh installation access token.
+    async fn mint_installation_token(&self) -> (String, SystemTime) {
+        let jwt_client = github_client(Credentials::JWT(self.jwt.clone()));
+
+        let access = jwt_client.app().make_access_token(self.installation_id).await.unwrap();
+
+        // GitHub returns `expires_at` as an RFC 3339 timestamp, not a
+        // seconds-from-now duration.
+        let expires_at = DateTime::parse_from_rfc3339(&access.expires_at)
+            .map(|dt| SystemTime::UNIX_EPOCH + Duration::from_secs(dt.timestamp().max(0) as u64))
+            .unwrap_or_else(|_| SystemTime::now());
+
+        (access.token, expires_at)
+    }
+}
+
+/// Build a `Github` client sharing our usual HTTP cache, differing only in
+/// which credentials it authenticates with.
+fn github_client(credentials: Credentials) -> Github {
+    let http_cache = Box::new(FileBasedCache::new(format!("{}/.cache/github", env::var("HOME").unwrap())));
+    Github::custom(
+        "https://api.github.com",
+        concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
+        credentials,
+        Client::builder().build().unwrap(),
+        http_cache,
+    )
+}
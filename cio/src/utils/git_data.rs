@@ -0,0 +1,158 @@
+use std::error::Error;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use tracing::instrument;
+
+use crate::utils::retry_http;
+
+/// Boxed error type for the Git Data API calls below: a mix of `reqwest`
+/// transport/status errors and JSON decode errors, none of which this repo
+/// has a dedicated error enum for.
+type GitDataError = Box<dyn Error + Send + Sync>;
+
+/// Await a `retry_http` result, turn a non-2xx response into an error, and
+/// decode the body as JSON.
+async fn fetch_json<T: serde::de::DeserializeOwned>(resp: Result<reqwest::Response, reqwest::Error>) -> Result<T, GitDataError> {
+    Ok(resp?.error_for_status()?.json().await?)
+}
+
+/// Git's blob object hash: `sha1("blob " + len + "\0" + content)`. Computing
+/// this locally lets us know whether a file actually changed without having
+/// to create a blob for it first.
+fn git_blob_sha(content: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(format!("blob {}\0", content.len()).as_bytes());
+    hasher.update(content);
+    hex::encode(hasher.finalize())
+}
+
+#[derive(Debug, Deserialize)]
+struct GitObject {
+    sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitReference {
+    object: GitObject,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitCommitRef {
+    tree: GitObject,
+}
+
+#[derive(Debug, Deserialize)]
+struct TreeEntry {
+    path: String,
+    sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitTree {
+    tree: Vec<TreeEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct NewTreeEntry {
+    path: String,
+    mode: &'static str,
+    #[serde(rename = "type")]
+    typ: &'static str,
+    sha: String,
+}
+
+fn api_url(owner: &str, repo: &str, path: &str) -> String {
+    format!("https://api.github.com/repos/{}/{}/{}", owner, repo, path)
+}
+
+/// Commit a set of file additions/updates to a branch as a single commit via
+/// the Git Data API. Returns the new commit sha, or `None` if nothing changed.
+#[instrument(skip(files))]
+pub async fn commit_files_in_github_repo(token: &str, owner: &str, repo: &str, branch: &str, files: Vec<(String, Vec<u8>)>, message: &str) -> Result<Option<String>, GitDataError> {
+    if files.is_empty() {
+        return Ok(None);
+    }
+
+    let client = reqwest::Client::builder().user_agent(concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"))).build()?;
+
+    let reference: GitReference = fetch_json(retry_http(|| client.get(&api_url(owner, repo, &format!("git/ref/heads/{}", branch))).bearer_auth(token).send()).await).await?;
+    let parent_sha = reference.object.sha;
+
+    let parent_commit: GitCommitRef = fetch_json(retry_http(|| client.get(&api_url(owner, repo, &format!("git/commits/{}", parent_sha))).bearer_auth(token).send()).await).await?;
+
+    let base_tree: GitTree = fetch_json(retry_http(|| client.get(&api_url(owner, repo, &format!("git/trees/{}?recursive=1", parent_commit.tree.sha))).bearer_auth(token).send()).await).await?;
+
+    let mut entries = Vec::new();
+    for (path, content) in files {
+        let new_sha = git_blob_sha(&content);
+        let unchanged = base_tree.tree.iter().any(|entry| entry.path == path && entry.sha == new_sha);
+        if unchanged {
+            println!("[github commit] {} is unchanged, skipping", path);
+            continue;
+        }
+
+        let blob: GitObject = fetch_json(
+            retry_http(|| {
+                client
+                    .post(&api_url(owner, repo, "git/blobs"))
+                    .bearer_auth(token)
+                    .json(&json!({ "content": base64::encode(&content), "encoding": "base64" }))
+                    .send()
+            })
+            .await,
+        )
+        .await?;
+
+        entries.push(NewTreeEntry {
+            path,
+            mode: "100644",
+            typ: "blob",
+            sha: blob.sha,
+        });
+    }
+
+    if entries.is_empty() {
+        println!("[github commit] no files changed on {}, not creating a commit", branch);
+        return Ok(None);
+    }
+
+    let new_tree: GitObject = fetch_json(
+        retry_http(|| {
+            client
+                .post(&api_url(owner, repo, "git/trees"))
+                .bearer_auth(token)
+                .json(&json!({ "base_tree": parent_commit.tree.sha, "tree": entries }))
+                .send()
+        })
+        .await,
+    )
+    .await?;
+
+    let new_commit: GitObject = fetch_json(
+        retry_http(|| {
+            client
+                .post(&api_url(owner, repo, "git/commits"))
+                .bearer_auth(token)
+                .json(&json!({ "message": message, "tree": new_tree.sha, "parents": [parent_sha] }))
+                .send()
+        })
+        .await,
+    )
+    .await?;
+
+    retry_http(|| {
+        client
+            .patch(&api_url(owner, repo, &format!("git/refs/heads/{}", branch)))
+            .bearer_auth(token)
+            .json(&json!({ "sha": new_commit.sha, "force": false }))
+            .send()
+    })
+    .await?
+    .error_for_status()?;
+
+    println!("[github commit] created commit {} on {} ({} file(s) changed)", new_commit.sha, branch, entries.len());
+
+    Ok(Some(new_commit.sha))
+}
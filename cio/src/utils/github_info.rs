@@ -0,0 +1,303 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LINK};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::utils::retry_http;
+
+/// A GitHub user, as returned by `GET /users/:handle`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GithubUser {
+    pub login: String,
+    pub id: u64,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub company: Option<String>,
+}
+
+/// A GitHub organization, as returned by `GET /orgs/:name`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GithubOrg {
+    pub login: String,
+    pub id: u64,
+    pub description: Option<String>,
+}
+
+/// The author/committer of a commit, as embedded in `GithubCommit`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GithubCommitPerson {
+    pub name: Option<String>,
+    pub email: Option<String>,
+}
+
+/// The nested `commit` object of a `GET /repos/:owner/:repo/commits` entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GithubCommitDetail {
+    pub author: Option<GithubCommitPerson>,
+    pub committer: Option<GithubCommitPerson>,
+    pub message: String,
+}
+
+/// A single commit, as returned by `GET /repos/:owner/:repo/commits`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GithubCommit {
+    pub sha: String,
+    pub commit: GithubCommitDetail,
+}
+
+/// A release, as returned by `GET /repos/:owner/:repo/releases`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GithubRelease {
+    pub id: u64,
+    pub tag_name: String,
+    pub name: Option<String>,
+    pub draft: bool,
+    pub prerelease: bool,
+}
+
+/// A contributor, as returned by `GET /repos/:owner/:repo/contributors`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GithubContributor {
+    pub login: String,
+    pub id: u64,
+    pub contributions: u64,
+}
+
+/// Parse the `rel="next"` URL out of a GitHub `Link` response header, if present.
+fn next_page_url(resp: &reqwest::Response) -> Option<String> {
+    let link = resp.headers().get(LINK)?.to_str().ok()?;
+    link.split(',').find_map(|part| {
+        let (url, rel) = part.split_once(';')?;
+        if rel.trim() == r#"rel="next""# {
+            Some(url.trim().trim_start_matches('<').trim_end_matches('>').to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// What we persist on disk for one cached request path: either a confirmed
+/// 404 (so we stop asking), or the last response body we saw along with the
+/// validators needed to make a conditional request next time.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheEntry {
+    not_found: bool,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+}
+
+/// Typed, individually-cached accessors for GitHub metadata that hubcaps
+/// doesn't expose.
+pub struct GithubInfo {
+    token: String,
+    client: Client,
+    cache_dir: PathBuf,
+}
+
+impl GithubInfo {
+    /// Create a new `GithubInfo`, caching responses under `~/.cache/github_info`.
+    pub fn new(token: &str) -> Self {
+        let cache_dir = PathBuf::from(format!("{}/.cache/github_info", env::var("HOME").unwrap()));
+        fs::create_dir_all(&cache_dir).unwrap();
+
+        GithubInfo {
+            token: token.to_string(),
+            client: Client::new(),
+            cache_dir,
+        }
+    }
+
+    fn cache_path(&self, path: &str) -> PathBuf {
+        self.cache_dir.join(path.replace('/', "_"))
+    }
+
+    fn read_cache(&self, path: &str) -> Option<CacheEntry> {
+        let bytes = fs::read(self.cache_path(path)).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    fn write_cache(&self, path: &str, entry: &CacheEntry) {
+        if let Ok(bytes) = serde_json::to_vec(entry) {
+            fs::write(self.cache_path(path), bytes).ok();
+        }
+    }
+
+    /// Build the (conditional, if we have a cache entry) GET request for `path`.
+    fn request(&self, path: &str, cached: &Option<CacheEntry>) -> RequestBuilder {
+        let mut request = self
+            .client
+            .get(&format!("https://api.github.com/{}", path))
+            .bearer_auth(&self.token)
+            .header("User-Agent", concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")));
+
+        if let Some(entry) = cached {
+            if let Some(etag) = &entry.etag {
+                request = request.header(IF_NONE_MATCH, etag.clone());
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified.clone());
+            }
+        }
+
+        request
+    }
+
+    /// GET the GitHub API path `path` (e.g. `users/foo`), using our on-disk
+    /// cache and conditional requests, and deserialize the result as `T`.
+    /// Returns `None` if the path 404s (including on a cached negative hit)
+    /// or if the request keeps failing after `retry_http`'s backoff gives up.
+    #[instrument(skip(self))]
+    async fn get_cached<T: DeserializeOwned>(&self, path: &str) -> Option<T> {
+        self.get_cached_with_link(path).await.0
+    }
+
+    /// Like `get_cached`, but also returns the `rel="next"` URL from the
+    /// response's `Link` header (if any), for callers that need to paginate.
+    #[instrument(skip(self))]
+    async fn get_cached_with_link<T: DeserializeOwned>(&self, path: &str) -> (Option<T>, Option<String>) {
+        let cached = self.read_cache(path);
+        if let Some(entry) = &cached {
+            if entry.not_found {
+                return (None, None);
+            }
+        }
+
+        let resp = match retry_http(|| self.request(path, &cached).send()).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                println!("[github info] request for {} failed: {:?}", path, e);
+                return (None, None);
+            }
+        };
+
+        match resp.status() {
+            StatusCode::NOT_MODIFIED => {
+                println!("[github info] cache hit (304) for {}", path);
+                let next = next_page_url(&resp);
+                (cached.and_then(|entry| serde_json::from_str(&entry.body).ok()), next)
+            }
+            StatusCode::NOT_FOUND => {
+                println!("[github info] {} does not exist, caching the miss", path);
+                self.write_cache(path, &CacheEntry { not_found: true, ..Default::default() });
+                (None, None)
+            }
+            status if status.is_success() => {
+                let etag = resp.headers().get(ETAG).and_then(|v| v.to_str().ok()).map(|s| s.to_string());
+                let last_modified = resp.headers().get(LAST_MODIFIED).and_then(|v| v.to_str().ok()).map(|s| s.to_string());
+                let next = next_page_url(&resp);
+                let body = match resp.text().await {
+                    Ok(body) => body,
+                    Err(e) => {
+                        println!("[github info] reading body for {} failed: {:?}", path, e);
+                        return (None, None);
+                    }
+                };
+
+                self.write_cache(
+                    path,
+                    &CacheEntry {
+                        not_found: false,
+                        etag,
+                        last_modified,
+                        body: body.clone(),
+                    },
+                );
+
+                (serde_json::from_str(&body).ok(), next)
+            }
+            status => {
+                println!("[github info] {} returned {}, not caching", path, status);
+                (None, None)
+            }
+        }
+    }
+
+    /// GET a paginated list endpoint, following the `Link` header's
+    /// `rel="next"` URL until exhausted. Only the first page goes through
+    /// our on-disk ETag/Last-Modified cache; later pages are fetched fresh
+    /// each call since caching them would need a cache key per page URL.
+    #[instrument(skip(self))]
+    async fn get_cached_paged<T: DeserializeOwned>(&self, path: &str) -> Option<Vec<T>> {
+        let (first, mut next) = self.get_cached_with_link::<Vec<T>>(path).await;
+        let mut items = first?;
+
+        while let Some(url) = next.take() {
+            let resp = match retry_http(|| {
+                self.client
+                    .get(&url)
+                    .bearer_auth(&self.token)
+                    .header("User-Agent", concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")))
+                    .send()
+            })
+            .await
+            {
+                Ok(resp) => resp,
+                Err(e) => {
+                    println!("[github info] paginated request to {} failed: {:?}", url, e);
+                    break;
+                }
+            };
+
+            if !resp.status().is_success() {
+                println!("[github info] paginated request to {} returned {}", url, resp.status());
+                break;
+            }
+
+            next = next_page_url(&resp);
+            match resp.json::<Vec<T>>().await {
+                Ok(mut page) => items.append(&mut page),
+                Err(e) => {
+                    println!("[github info] decoding paginated page from {} failed: {:?}", url, e);
+                    break;
+                }
+            }
+        }
+
+        Some(items)
+    }
+
+    /// Look up a GitHub user by their handle.
+    pub async fn user(&self, handle: &str) -> Option<GithubUser> {
+        self.get_cached(&format!("users/{}", handle)).await
+    }
+
+    /// Look up a GitHub organization by name.
+    pub async fn org(&self, name: &str) -> Option<GithubOrg> {
+        self.get_cached(&format!("orgs/{}", name)).await
+    }
+
+    /// List the organizations a user belongs to.
+    pub async fn user_orgs(&self, handle: &str) -> Option<Vec<GithubOrg>> {
+        self.get_cached(&format!("users/{}/orgs", handle)).await
+    }
+
+    /// List commits on a repo's default branch, across all pages.
+    pub async fn commits(&self, owner: &str, repo: &str) -> Option<Vec<GithubCommit>> {
+        self.get_cached_paged(&format!("repos/{}/{}/commits?per_page=100", owner, repo)).await
+    }
+
+    /// List releases for a repo, across all pages.
+    pub async fn releases(&self, owner: &str, repo: &str) -> Option<Vec<GithubRelease>> {
+        self.get_cached_paged(&format!("repos/{}/{}/releases?per_page=100", owner, repo)).await
+    }
+
+    /// List contributors for a repo, across all pages.
+    pub async fn contributors(&self, owner: &str, repo: &str) -> Option<Vec<GithubContributor>> {
+        self.get_cached_paged(&format!("repos/{}/{}/contributors?per_page=100", owner, repo)).await
+    }
+
+    /// Collect the distinct commit author emails for a repo.
+    pub async fn commit_emails(&self, owner: &str, repo: &str) -> Option<Vec<String>> {
+        let commits = self.commits(owner, repo).await?;
+        let mut emails: Vec<String> = commits.into_iter().filter_map(|c| c.commit.author.and_then(|a| a.email)).collect();
+        emails.sort();
+        emails.dedup();
+        Some(emails)
+    }
+}
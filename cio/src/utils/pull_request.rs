@@ -0,0 +1,109 @@
+use std::error::Error;
+
+use futures_util::stream::TryStreamExt;
+use hubcaps::issues::State;
+use hubcaps::pulls::{Pull, PullEditOptions, PullListOptions, PullOptions};
+use hubcaps::repositories::Repository;
+use serde::Deserialize;
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use tracing::instrument;
+
+use crate::utils::{commit_files_in_github_repo, retry_github, retry_http};
+
+/// Boxed error type covering both the raw Git Data API calls made here and
+/// the hubcaps calls made against the PR endpoints.
+type PullRequestError = Box<dyn Error + Send + Sync>;
+
+/// Returns the first open PR whose title contains `search`, analogous to
+/// `check_if_github_issue_exists`.
+#[instrument(skip(prs))]
+pub fn check_if_github_pr_exists<'a>(prs: &'a [Pull], search: &str) -> Option<&'a Pull> {
+    prs.iter().find(|p| p.title.contains(search))
+}
+
+/// Derive a stable topic branch name from the set of paths being changed, so
+/// repeated runs over the same logical change reuse the same branch instead
+/// of piling up new ones.
+fn topic_branch_name(paths: &[String]) -> String {
+    let mut sorted = paths.to_vec();
+    sorted.sort();
+
+    let mut hasher = Sha1::new();
+    for path in &sorted {
+        hasher.update(path.as_bytes());
+        hasher.update(b"\0");
+    }
+
+    format!("cio/update-{}", &hex::encode(hasher.finalize())[..12])
+}
+
+#[derive(Debug, Deserialize)]
+struct GitObject {
+    sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitReference {
+    object: GitObject,
+}
+
+/// Make sure `topic_branch` exists, branching it from the tip of
+/// `base_branch` if it doesn't. hubcaps' `Git` handle can only read refs, not
+/// create them, so this talks to the Git Data API directly.
+async fn ensure_topic_branch_exists(client: &reqwest::Client, token: &str, owner: &str, repo: &str, base_branch: &str, topic_branch: &str) -> Result<(), PullRequestError> {
+    let ref_url = |b: &str| format!("https://api.github.com/repos/{}/{}/git/ref/heads/{}", owner, repo, b);
+
+    if retry_http(|| client.get(&ref_url(topic_branch)).bearer_auth(token).send()).await.map(|r| r.status().is_success()).unwrap_or(false) {
+        return Ok(());
+    }
+
+    let base_ref: GitReference = retry_http(|| client.get(&ref_url(base_branch)).bearer_auth(token).send()).await?.error_for_status()?.json().await?;
+
+    retry_http(|| {
+        client
+            .post(&format!("https://api.github.com/repos/{}/{}/git/refs", owner, repo))
+            .bearer_auth(token)
+            .json(&json!({ "ref": format!("refs/heads/{}", topic_branch), "sha": base_ref.object.sha }))
+            .send()
+    })
+    .await?
+    .error_for_status()?;
+
+    Ok(())
+}
+
+/// Change a set of files via a pull request instead of committing straight to
+/// `base_branch`, updating an existing open PR for the same change in place
+/// if one exists instead of opening a duplicate.
+#[instrument(skip(repo, files))]
+pub async fn open_or_update_pull_request_with_files(repo: &Repository, token: &str, owner: &str, repo_name: &str, base_branch: &str, files: Vec<(String, Vec<u8>)>, title: &str, body: &str) -> Result<(), PullRequestError> {
+    let paths: Vec<String> = files.iter().map(|(path, _)| path.clone()).collect();
+    let topic_branch = topic_branch_name(&paths);
+
+    let client = reqwest::Client::builder().user_agent(concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"))).build()?;
+    ensure_topic_branch_exists(&client, token, owner, repo_name, base_branch, &topic_branch).await?;
+
+    let commit_sha = commit_files_in_github_repo(token, owner, repo_name, &topic_branch, files, &format!("{}\n\n{}", title, body)).await?;
+    if commit_sha.is_none() {
+        println!("[github pr] nothing changed for {:?}, leaving any existing PR alone", title);
+        return Ok(());
+    }
+
+    let open_prs = retry_github(|| repo.pulls().iter(&PullListOptions::builder().state(State::Open).build()).try_collect::<Vec<Pull>>()).await?;
+
+    match check_if_github_pr_exists(&open_prs, title) {
+        Some(pr) => {
+            retry_github(|| repo.pulls().get(pr.number).edit(&PullEditOptions::builder().title(title).body(body).build()))
+                .await
+                .ok();
+            println!("[github pr] updated existing PR #{} ({:?}) on {}", pr.number, title, topic_branch);
+        }
+        None => {
+            let pr = retry_github(|| repo.pulls().create(&PullOptions::new(title, &topic_branch, base_branch, Some(body)))).await?;
+            println!("[github pr] opened PR #{} ({:?}) on {}", pr.number, title, topic_branch);
+        }
+    }
+
+    Ok(())
+}
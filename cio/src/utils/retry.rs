@@ -0,0 +1,124 @@
+use std::cmp::min;
+use std::future::Future;
+use std::time::Duration;
+
+use rand::Rng;
+use tokio::time::{delay_for, Instant};
+use tracing::instrument;
+
+/// Starting interval for exponential backoff retries against the GitHub API.
+const INITIAL_INTERVAL: Duration = Duration::from_millis(500);
+/// Upper bound on the backoff interval between retries against the GitHub API.
+const MAX_INTERVAL: Duration = Duration::from_secs(60);
+/// Give up retrying a GitHub API call after this much total time has elapsed.
+const MAX_ELAPSED_TIME: Duration = Duration::from_secs(15 * 60);
+
+/// Sleep for `jittered`, doubling `interval` (capped at `MAX_INTERVAL`) for
+/// next time. Shared by `retry_github` and `retry_http` so both backoff the
+/// same way.
+async fn backoff(interval: &mut Duration) {
+    let jittered = Duration::from_millis(rand::thread_rng().gen_range(0..interval.as_millis() as u64));
+    *interval = min(*interval * 2, MAX_INTERVAL);
+    delay_for(jittered).await;
+}
+
+/// Returns true if `error` represents a transient failure worth retrying
+/// (rate limits, secondary rate limits / abuse detection, 5xx) as opposed to
+/// a permanent one (404, 422, etc) that should be surfaced immediately.
+fn is_retryable(error: &hubcaps::errors::Error) -> bool {
+    match error {
+        hubcaps::errors::Error::RateLimit { .. } => true,
+        hubcaps::errors::Error::Fault { code, error } => {
+            code.is_server_error() || error.message.to_lowercase().contains("secondary rate limit") || error.message.to_lowercase().contains("abuse")
+        }
+        _ => false,
+    }
+}
+
+/// Retry a GitHub API call with exponential backoff and full jitter.
+///
+/// `op` is called repeatedly until it succeeds, returns a non-retryable
+/// error, or `MAX_ELAPSED_TIME` has passed since the first attempt. The
+/// backoff interval starts at `INITIAL_INTERVAL`, doubles on every retryable
+/// failure up to `MAX_INTERVAL`, and the actual sleep is chosen uniformly
+/// from `[0, interval)`. An explicit `RateLimit { reset }` error instead
+/// sleeps until `reset` since GitHub tells us exactly when it will lift the
+/// limit.
+#[instrument(skip(op))]
+pub async fn retry_github<F, Fut, T>(op: F) -> Result<T, hubcaps::errors::Error>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, hubcaps::errors::Error>>,
+{
+    let start = Instant::now();
+    let mut interval = INITIAL_INTERVAL;
+
+    loop {
+        match op().await {
+            Ok(v) => return Ok(v),
+            Err(e) => {
+                if !is_retryable(&e) {
+                    return Err(e);
+                }
+
+                if start.elapsed() >= MAX_ELAPSED_TIME {
+                    println!("[retry] giving up after {:?}, last error: {:?}", start.elapsed(), e);
+                    return Err(e);
+                }
+
+                if let hubcaps::errors::Error::RateLimit { reset } = &e {
+                    println!("[retry] rate limited, sleeping until reset in {}s", reset.as_secs());
+                    delay_for(*reset + Duration::from_secs(5)).await;
+                } else {
+                    println!("[retry] retryable error, backing off: {:?}", e);
+                    backoff(&mut interval).await;
+                }
+            }
+        }
+    }
+}
+
+/// Returns true if a 403 response looks like GitHub's secondary rate limit /
+/// abuse detection rather than a genuine permission error: GitHub marks those
+/// with a `Retry-After` header or an exhausted `X-RateLimit-Remaining`, which
+/// a plain permission-denied 403 won't have.
+fn is_retryable_forbidden(resp: &reqwest::Response) -> bool {
+    resp.headers().contains_key(reqwest::header::RETRY_AFTER) || resp.headers().get("x-ratelimit-remaining").and_then(|v| v.to_str().ok()) == Some("0")
+}
+
+/// Retry a raw `reqwest` call (used where we talk to the GitHub API directly
+/// instead of through hubcaps, e.g. the Git Data API write endpoints) with
+/// the same exponential-backoff-with-jitter policy as `retry_github`.
+///
+/// A connection-level `Err` is always retried, as is a 5xx or 429 response.
+/// A 403 is only retried when it looks like a secondary rate limit / abuse
+/// detection response (see `is_retryable_forbidden`); a genuine
+/// permission-denied 403 is returned immediately, matching `retry_github`.
+#[instrument(skip(op))]
+pub async fn retry_http<F, Fut>(op: F) -> Result<reqwest::Response, reqwest::Error>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<reqwest::Response, reqwest::Error>>,
+{
+    let start = Instant::now();
+    let mut interval = INITIAL_INTERVAL;
+
+    loop {
+        let result = op().await;
+
+        let retry = match &result {
+            Ok(resp) => {
+                let status = resp.status();
+                status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS || (status == reqwest::StatusCode::FORBIDDEN && is_retryable_forbidden(resp))
+            }
+            Err(_) => true,
+        };
+
+        if !retry || start.elapsed() >= MAX_ELAPSED_TIME {
+            return result;
+        }
+
+        println!("[retry] retryable HTTP failure, backing off: {:?}", result.as_ref().map(|r| r.status()));
+        backoff(&mut interval).await;
+    }
+}